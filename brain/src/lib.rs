@@ -1,9 +1,19 @@
-use kinematics::JointState;
+use kinematics::opw_kinematics::OpwKinematicsSolver;
+use nalgebra::Isometry3;
 use std::collections::VecDeque;
+use std::fmt;
+
+/// A full 6-DOF manipulator configuration tagged with the time, in seconds, at
+/// which it should be reached relative to the start of the trajectory.
+#[derive(Debug, Clone, Copy)]
+pub struct TimedWaypoint {
+    pub time_from_start: f64,
+    pub angles: [f64; 6],
+}
 
 #[derive(Debug)]
 pub struct Planner {
-    trajectory: VecDeque<JointState>,
+    trajectory: VecDeque<TimedWaypoint>,
 }
 
 impl Planner {
@@ -13,33 +23,206 @@ impl Planner {
         }
     }
 
-    pub fn add_waypoint(&mut self, state: JointState) {
-        self.trajectory.push_back(state);
+    /// Pushes a timed 6-DOF configuration onto the trajectory, tagged with the
+    /// time it should be reached, measured from the start of motion.
+    pub fn add_waypoint(&mut self, time_from_start: f64, angles: [f64; 6]) {
+        self.trajectory.push_back(TimedWaypoint {
+            time_from_start,
+            angles,
+        });
     }
 
-    pub fn next_step(&mut self) -> Option<JointState> {
-        self.trajectory.pop_front()
+    /// Samples the trajectory at elapsed clock `traj_clock` (seconds from the
+    /// start of motion), interpolating each joint linearly within its active
+    /// segment and holding the final configuration once the clock is past the
+    /// last waypoint; see [`Planner::is_finished`].
+    pub fn sample(&self, traj_clock: f64) -> [f64; 6] {
+        if self.trajectory.is_empty() {
+            return [0.0; 6];
+        }
+
+        // Locate the segment containing `traj_clock`.
+        let mut i = 0;
+        while i + 1 < self.trajectory.len()
+            && traj_clock >= self.trajectory[i + 1].time_from_start
+        {
+            i += 1;
+        }
+
+        if i + 1 >= self.trajectory.len() {
+            return self.trajectory[self.trajectory.len() - 1].angles;
+        }
+
+        let start = self.trajectory[i];
+        let end = self.trajectory[i + 1];
+        let dt = end.time_from_start - start.time_from_start;
+        if dt <= 0.0 {
+            return end.angles;
+        }
+
+        let alpha = ((traj_clock - start.time_from_start) / dt).clamp(0.0, 1.0);
+        let mut out = [0.0; 6];
+        for j in 0..6 {
+            out[j] = start.angles[j] + alpha * (end.angles[j] - start.angles[j]);
+        }
+        out
+    }
+
+    /// Returns true once `traj_clock` is at or beyond the final waypoint, or
+    /// when no trajectory has been planned.
+    pub fn is_finished(&self, traj_clock: f64) -> bool {
+        match self.trajectory.back() {
+            Some(last) => traj_clock >= last.time_from_start,
+            None => true,
+        }
     }
 }
 
+/// Error returned when a motion plan cannot be produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// No valid IK solution was found for the pose at this index in the input.
+    NoSolution { pose_index: usize },
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::NoSolution { pose_index } => {
+                write!(f, "no valid IK solution for pose at index {}", pose_index)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// Outcome of comparing commanded joint targets against measured feedback while
+/// executing a trajectory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExecutionStatus {
+    /// The trajectory is still running and every joint is tracking within the
+    /// path tolerance.
+    InProgress,
+    /// The trajectory drained and every joint settled within the goal tolerance.
+    Succeeded,
+    /// A joint's tracking error exceeded the active tolerance; motion must stop.
+    Aborted { joint: usize, error: f64 },
+}
+
 pub struct RobotBrain {
     planner: Planner,
+    /// Trajectory clock, in seconds, advanced by the control loop each tick.
+    traj_clock: f64,
 }
 
 impl RobotBrain {
     pub fn new() -> Self {
         Self {
             planner: Planner::new(),
+            traj_clock: 0.0,
         }
     }
 
-    pub fn plan_motion(&mut self, target: JointState) {
-        // Simple planner: Just move directly to target
-        self.planner.add_waypoint(target);
+    /// Plans a Cartesian path by solving IK for each target pose in order.
+    ///
+    /// The solver picks the configuration nearest to the previous joint state
+    /// (seeded with `current` for the first pose), which keeps the resulting
+    /// joint trajectory continuous. The trajectory is anchored with a `t = 0`
+    /// waypoint at `current` so the opening segment interpolates from where the
+    /// robot actually is; each IK solution then follows one second apart. If any
+    /// pose has no valid IK solution, planning aborts with
+    /// [`PlanError::NoSolution`] identifying the offending pose rather than
+    /// silently dropping it.
+    pub fn plan_cartesian_path(
+        &mut self,
+        poses: &[Isometry3<f64>],
+        solver: &OpwKinematicsSolver,
+        current: &[f64; 6],
+    ) -> Result<(), PlanError> {
+        self.traj_clock = 0.0;
+        // Anchor the start of the trajectory at the current configuration.
+        self.planner.add_waypoint(0.0, *current);
+        let mut previous = *current;
+        for (index, pose) in poses.iter().enumerate() {
+            let solution = solver
+                .best_solution(pose, &previous)
+                .ok_or(PlanError::NoSolution { pose_index: index })?;
+            let time_from_start = (index + 1) as f64;
+            self.planner.add_waypoint(time_from_start, solution);
+            previous = solution;
+        }
+        Ok(())
     }
 
-    pub fn execute_next_step(&mut self) -> Option<JointState> {
-        self.planner.next_step()
+    /// Compares commanded joint targets against measured feedback and reports
+    /// execution progress.
+    ///
+    /// While the trajectory is still running (`finished == false`) any joint
+    /// whose tracking error exceeds `path_tolerance` aborts the motion. Once the
+    /// planner has drained, success requires every joint to be within
+    /// `goal_tolerance`; otherwise the first out-of-tolerance joint is reported.
+    /// Errors are measured as the shortest angular distance so wrap-around does
+    /// not look like a large deviation.
+    pub fn tracking_status(
+        commanded: &[f64],
+        feedback: &[f64],
+        finished: bool,
+        path_tolerance: f64,
+        goal_tolerance: f64,
+    ) -> ExecutionStatus {
+        let tolerance = if finished {
+            goal_tolerance
+        } else {
+            path_tolerance
+        };
+        let n = commanded.len().min(feedback.len());
+        for joint in 0..n {
+            let d = commanded[joint] - feedback[joint];
+            let error = d.sin().atan2(d.cos()).abs();
+            if error > tolerance {
+                return ExecutionStatus::Aborted { joint, error };
+            }
+        }
+        if finished {
+            ExecutionStatus::Succeeded
+        } else {
+            ExecutionStatus::InProgress
+        }
+    }
+
+    /// Drives one closed-loop step of the planned Cartesian trajectory.
+    ///
+    /// Samples the commanded 6-DOF configuration at the current clock, compares
+    /// it against the measured `feedback`, and advances the clock by `dt` while
+    /// the motion is still in progress. Returns the commanded configuration (for
+    /// publishing) alongside the resulting [`ExecutionStatus`]; the command is
+    /// `None` once the trajectory has drained.
+    pub fn execute_cartesian_step(
+        &mut self,
+        dt: f64,
+        feedback: &[f64; 6],
+        path_tolerance: f64,
+        goal_tolerance: f64,
+    ) -> (Option<[f64; 6]>, ExecutionStatus) {
+        let finished = self.planner.is_finished(self.traj_clock);
+        let commanded = self.planner.sample(self.traj_clock);
+        let status = Self::tracking_status(
+            &commanded,
+            feedback,
+            finished,
+            path_tolerance,
+            goal_tolerance,
+        );
+
+        if finished {
+            (None, status)
+        } else {
+            if status == ExecutionStatus::InProgress {
+                self.traj_clock += dt;
+            }
+            (Some(commanded), status)
+        }
     }
 }
 
@@ -48,13 +231,87 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_planner() {
+    fn test_planner_interpolates_within_segment() {
+        let mut planner = Planner::new();
+        planner.add_waypoint(0.0, [0.0; 6]);
+        planner.add_waypoint(2.0, [2.0, 4.0, 0.0, 0.0, 0.0, 0.0]);
+
+        let mid = planner.sample(1.0);
+        assert!((mid[0] - 1.0).abs() < 1e-9);
+        assert!((mid[1] - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_planner_holds_final_position() {
+        let mut planner = Planner::new();
+        planner.add_waypoint(0.0, [0.0; 6]);
+        planner.add_waypoint(1.0, [1.5, 0.0, 0.0, 0.0, 0.0, 0.0]);
+
+        let held = planner.sample(5.0);
+        assert!((held[0] - 1.5).abs() < 1e-9);
+        assert!(planner.is_finished(5.0));
+    }
+
+    fn test_solver() -> OpwKinematicsSolver {
+        // Parameters for a generic robot (similar to Kuka KR6).
+        OpwKinematicsSolver::new(0.550, 0.550, 0.600, 0.110, 0.150, 0.000, 0.000)
+    }
+
+    #[test]
+    fn test_plan_cartesian_path_populates_trajectory() {
+        let solver = test_solver();
+        let current = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        let a = solver.forward_kinematics(&current);
+        let b = solver.forward_kinematics(&[0.2, 0.2, 0.3, 0.4, 0.5, 0.6]);
+
+        let mut brain = RobotBrain::new();
+        brain
+            .plan_cartesian_path(&[a, b], &solver, &current)
+            .expect("path should plan");
+
+        assert!(!brain.planner.is_finished(0.0));
+        // The trajectory is anchored at `current` (t = 0), so sampling there
+        // recovers the seeded configuration exactly.
+        let start = brain.planner.sample(0.0);
+        for (q, expected) in start.iter().zip(current.iter()) {
+            let d = q - expected;
+            let wrapped = d.sin().atan2(d.cos());
+            assert!(wrapped.abs() < 1e-9, "joint off by {}", wrapped);
+        }
+    }
+
+    #[test]
+    fn test_plan_cartesian_path_reports_unreachable_pose() {
+        let solver = test_solver();
+        let current = [0.0; 6];
+        // A pose far outside the workspace has no IK solution.
+        let unreachable = Isometry3::translation(100.0, 100.0, 100.0);
+
         let mut brain = RobotBrain::new();
-        let target = JointState { angle: 1.0, ..Default::default() };
-        brain.plan_motion(target);
+        let err = brain
+            .plan_cartesian_path(&[unreachable], &solver, &current)
+            .unwrap_err();
+        assert_eq!(err, PlanError::NoSolution { pose_index: 0 });
+    }
+
+    #[test]
+    fn test_tracking_status_in_progress() {
+        let status = RobotBrain::tracking_status(&[1.0, 2.0], &[1.01, 1.99], false, 0.1, 0.01);
+        assert_eq!(status, ExecutionStatus::InProgress);
+    }
+
+    #[test]
+    fn test_tracking_status_path_abort() {
+        let status = RobotBrain::tracking_status(&[1.0, 2.0], &[1.0, 1.5], false, 0.1, 0.01);
+        assert_eq!(status, ExecutionStatus::Aborted { joint: 1, error: 0.5 });
+    }
+
+    #[test]
+    fn test_tracking_status_goal_success_and_failure() {
+        let ok = RobotBrain::tracking_status(&[1.0], &[1.005], true, 0.1, 0.01);
+        assert_eq!(ok, ExecutionStatus::Succeeded);
 
-        let next_step = brain.execute_next_step();
-        assert!(next_step.is_some());
-        assert_eq!(next_step.unwrap().angle, 1.0);
+        let bad = RobotBrain::tracking_status(&[1.0], &[1.05], true, 0.1, 0.01);
+        assert!(matches!(bad, ExecutionStatus::Aborted { joint: 0, .. }));
     }
 }
@@ -3,7 +3,9 @@ use edgefirst_schemas::sensor_msgs::Image;
 use edgefirst_schemas::std_msgs::Header;
 use kinematics::JointState as KinematicsJointState;
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use zenoh::Session;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -35,10 +37,37 @@ pub struct CommunicationLayer {
     joint_state_key: String,
     joint_command_key: String,
     image_key: String,
+    /// Monotonic instant at which each joint was last heard from, keyed by the
+    /// joint `name`. Shared with the subscriber task so freshness can be polled.
+    last_updates: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Smoothing factor `a` of the first-order velocity filter, in `(0, 1]`.
+    velocity_alpha: f64,
+    /// Previous positions and filtered velocities used when a message carries no
+    /// usable velocity. Shared with the subscriber task.
+    velocity_estimator: Arc<Mutex<VelocityEstimator>>,
 }
 
 const ROS2_CDR_HEADER_LE: [u8; 4] = [0x00, 0x01, 0x00, 0x00];
 
+/// Default smoothing factor for the first-order velocity filter.
+const DEFAULT_VELOCITY_ALPHA: f64 = 0.2;
+
+/// Per-joint memory for numerically differentiating and smoothing velocity.
+#[derive(Default)]
+struct JointVelocityState {
+    prev_position: f64,
+    filtered_velocity: f64,
+}
+
+/// Stateful estimator that fills in missing joint velocities by differencing
+/// consecutive positions over the header timestamp delta and low-pass filtering
+/// the result. Keyed by joint name and carried between samples.
+#[derive(Default)]
+struct VelocityEstimator {
+    last_stamp: Option<f64>,
+    joints: HashMap<String, JointVelocityState>,
+}
+
 impl CommunicationLayer {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
         let config = zenoh::config::Config::default();
@@ -49,6 +78,9 @@ impl CommunicationLayer {
             joint_state_key: "rt/robot/joint_states".to_string(),
             joint_command_key: "rt/robot/joint_commands".to_string(),
             image_key: "rt/camera/image_raw".to_string(),
+            last_updates: Arc::new(Mutex::new(HashMap::new())),
+            velocity_alpha: DEFAULT_VELOCITY_ALPHA,
+            velocity_estimator: Arc::new(Mutex::new(VelocityEstimator::default())),
         })
     }
 
@@ -81,12 +113,25 @@ impl CommunicationLayer {
             .await
             .map_err(|e| e.to_string())?;
 
+        let last_updates = Arc::clone(&self.last_updates);
+        let velocity_estimator = Arc::clone(&self.velocity_estimator);
+        let velocity_alpha = self.velocity_alpha;
         tokio::spawn(async move {
             while let Ok(sample) = subscriber.recv_async().await {
                 let payload = sample.payload().to_bytes();
                 match Self::deserialize_ros_payload::<JointState>(&payload) {
                     Ok(msg) => {
-                        let joints = Self::convert_from_ros_joint_state(&msg);
+                        let now = Instant::now();
+                        if let Ok(mut map) = last_updates.lock() {
+                            for name in &msg.name {
+                                map.insert(name.clone(), now);
+                            }
+                        }
+                        let joints = Self::convert_from_ros_joint_state(
+                            &msg,
+                            &velocity_estimator,
+                            velocity_alpha,
+                        );
                         callback(joints);
                     }
                     Err(e) => eprintln!("Failed to process JointState: {}", e),
@@ -96,6 +141,23 @@ impl CommunicationLayer {
         Ok(())
     }
 
+    /// Returns true only if every joint heard so far was updated within
+    /// `allowed`. Returns false if any tracked joint's last update is older
+    /// than `allowed`, or if no joint state has been received yet. The control
+    /// loop uses this as a safety gate before publishing commands on stale
+    /// feedback.
+    pub fn all_joints_updated(&self, allowed: Duration) -> bool {
+        let map = match self.last_updates.lock() {
+            Ok(map) => map,
+            Err(_) => return false,
+        };
+        if map.is_empty() {
+            return false;
+        }
+        let now = Instant::now();
+        map.values().all(|last| now.duration_since(*last) <= allowed)
+    }
+
     pub async fn subscribe_camera_image<F>(
         &self,
         callback: F,
@@ -157,24 +219,68 @@ impl CommunicationLayer {
         msg
     }
 
-    fn convert_from_ros_joint_state(msg: &JointState) -> Vec<KinematicsJointState> {
+    fn convert_from_ros_joint_state(
+        msg: &JointState,
+        estimator: &Arc<Mutex<VelocityEstimator>>,
+        alpha: f64,
+    ) -> Vec<KinematicsJointState> {
         let mut joints = Vec::new();
         let len = msg.position.len();
+
+        // Header stamp in seconds, and the delta since the previous message.
+        let stamp = msg.header.stamp.sec as f64 + msg.header.stamp.nanosec as f64 * 1e-9;
+        let mut estimator = match estimator.lock() {
+            Ok(estimator) => estimator,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let dt = estimator.last_stamp.map(|prev| stamp - prev);
+
         for i in 0..len {
-            joints.push(KinematicsJointState {
-                angle: msg.position[i],
-                velocity: if i < msg.velocity.len() {
-                    msg.velocity[i]
-                } else {
-                    0.0
-                },
-                effort: if i < msg.effort.len() {
-                    msg.effort[i]
+            let position = msg.position[i];
+            let reported = msg.velocity.get(i).copied().unwrap_or(0.0);
+
+            // Trust a non-zero reported velocity; otherwise estimate it from the
+            // position difference over the timestamp delta, low-pass filtered.
+            let velocity = if reported != 0.0 {
+                reported
+            } else if let (Some(dt), Some(name)) = (dt, msg.name.get(i)) {
+                if dt > 0.0 {
+                    let state = estimator.joints.entry(name.clone()).or_default();
+                    let raw = (position - state.prev_position) / dt;
+                    state.filtered_velocity = alpha * raw + (1.0 - alpha) * state.filtered_velocity;
+                    state.filtered_velocity
                 } else {
-                    0.0
-                },
+                    // Non-positive delta: keep the last filtered estimate.
+                    estimator
+                        .joints
+                        .get(name)
+                        .map(|s| s.filtered_velocity)
+                        .unwrap_or(0.0)
+                }
+            } else {
+                0.0
+            };
+
+            joints.push(KinematicsJointState {
+                angle: position,
+                velocity,
+                effort: msg.effort.get(i).copied().unwrap_or(0.0),
             });
         }
+
+        // Advance the reference position and stamp together, and only when time
+        // moved forward (or this is the first sample). Leaving them in lockstep
+        // keeps the next differencing step referenced to a consistent instant;
+        // a duplicate or out-of-order stamp is ignored so it cannot desync them.
+        if dt.map(|d| d > 0.0).unwrap_or(true) {
+            for (i, name) in msg.name.iter().enumerate() {
+                if let Some(position) = msg.position.get(i) {
+                    estimator.joints.entry(name.clone()).or_default().prev_position = *position;
+                }
+            }
+            estimator.last_stamp = Some(stamp);
+        }
+
         joints
     }
 }
@@ -212,4 +318,42 @@ mod tests {
             "Payload too short for ROS 2 CDR header"
         );
     }
+
+    #[test]
+    fn test_velocity_estimated_from_positions() {
+        let estimator = Arc::new(Mutex::new(VelocityEstimator::default()));
+
+        // First sample seeds the previous position; no velocity yet.
+        let mut first = JointState {
+            name: vec!["joint_1".to_string()],
+            position: vec![0.0],
+            ..Default::default()
+        };
+        first.header.stamp = Time::new(0, 0);
+        let out = CommunicationLayer::convert_from_ros_joint_state(&first, &estimator, 0.2);
+        assert_eq!(out[0].velocity, 0.0);
+
+        // Second sample half a second later: raw rate 2.0, filtered by a=0.2.
+        let mut second = JointState {
+            name: vec!["joint_1".to_string()],
+            position: vec![1.0],
+            ..Default::default()
+        };
+        second.header.stamp = Time::new(0, 500_000_000);
+        let out = CommunicationLayer::convert_from_ros_joint_state(&second, &estimator, 0.2);
+        assert!((out[0].velocity - 0.4).abs() < 1e-9, "got {}", out[0].velocity);
+    }
+
+    #[test]
+    fn test_reported_velocity_is_preserved() {
+        let estimator = Arc::new(Mutex::new(VelocityEstimator::default()));
+        let msg = JointState {
+            name: vec!["joint_1".to_string()],
+            position: vec![0.5],
+            velocity: vec![1.25],
+            ..Default::default()
+        };
+        let out = CommunicationLayer::convert_from_ros_joint_state(&msg, &estimator, 0.2);
+        assert_eq!(out[0].velocity, 1.25);
+    }
 }
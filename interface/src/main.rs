@@ -1,12 +1,20 @@
 mod communication;
 
-use brain::RobotBrain;
+use brain::{ExecutionStatus, RobotBrain};
 use communication::CommunicationLayer;
+use kinematics::opw_kinematics::OpwKinematicsSolver;
 use kinematics::JointState;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use tokio::time::sleep;
 
+/// Per-joint tracking error allowed mid-motion before the motion is aborted.
+const PATH_TOLERANCE: f64 = 0.2;
+/// Per-joint error allowed at the end of the trajectory to declare success.
+const GOAL_TOLERANCE: f64 = 0.02;
+/// Maximum age of joint feedback before it is considered stale.
+const FEEDBACK_MAX_AGE: Duration = Duration::from_millis(500);
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     env_logger::init();
@@ -29,8 +37,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // CommunicationLayer is Arc-ed. But wait, subscribe takes &self.
     // The callback must be Send + Sync + 'static.
 
+    // Latest measured joint positions, updated from the subscriber callback and
+    // read by the control loop to close the loop against commanded targets.
+    let latest_feedback: Arc<Mutex<Vec<f64>>> = Arc::new(Mutex::new(Vec::new()));
+    let feedback_writer = Arc::clone(&latest_feedback);
+
     comms
-        .subscribe_joint_state(|joints| {
+        .subscribe_joint_state(move |joints| {
             // In a real system, we'd update internal state here
             // For now, just log
             // using println! instead of log::info! to see output in simulation if env_logger not configured
@@ -38,6 +51,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             if let Some(first) = joints.first() {
                 println!("Joint 0 angle: {:.2}", first.angle);
             }
+            if let Ok(mut fb) = feedback_writer.lock() {
+                *fb = joints.iter().map(|j| j.angle).collect();
+            }
         })
         .await?;
 
@@ -53,31 +69,70 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let mut brain = RobotBrain::new();
 
-    // Simulate a target
-    let target = JointState {
-        angle: 1.57,
-        ..Default::default()
-    };
-    println!("Planning motion to: {:?}", target);
-    brain.plan_motion(target);
+    // Plan a Cartesian path (parameters similar to a Kuka KR6). The target poses
+    // are derived by forward kinematics of two nearby configurations so the demo
+    // always has a reachable trajectory.
+    let solver = OpwKinematicsSolver::new(0.550, 0.550, 0.600, 0.110, 0.150, 0.000, 0.000);
+    let current = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+    let poses = [
+        solver.forward_kinematics(&[0.1, 0.2, 0.3, 0.4, 0.5, 0.6]),
+        solver.forward_kinematics(&[0.2, 0.3, 0.4, 0.5, 0.6, 0.7]),
+    ];
+    println!("Planning Cartesian path over {} poses", poses.len());
+    if let Err(e) = brain.plan_cartesian_path(&poses, &solver, &current) {
+        eprintln!("Failed to plan path: {}", e);
+        return Err(e.into());
+    }
 
     // Control Loop
     loop {
-        if let Some(state) = brain.execute_next_step() {
-            println!("Executing step: {:?}", state);
+        // Safety gate first: on stale feedback, hold without stepping the
+        // planner so a dead publisher cannot silently burn the trajectory clock
+        // (see req. joint-state freshness monitoring).
+        if !comms.all_joints_updated(FEEDBACK_MAX_AGE) {
+            println!("Joint feedback is stale; holding command.");
+            sleep(Duration::from_millis(100)).await;
+            continue;
+        }
 
-            // Publish command
-            // brain manages a single joint in this simplified example
-            let command = vec![state];
-            if let Err(e) = comms.publish_joint_command(&command).await {
-                eprintln!("Failed to publish command: {}", e);
+        // Latest measured configuration as a 6-DOF array, zero-padded if short.
+        let mut feedback = [0.0; 6];
+        if let Ok(fb) = latest_feedback.lock() {
+            for (slot, measured) in feedback.iter_mut().zip(fb.iter()) {
+                *slot = *measured;
             }
+        }
 
-            // Simulate hardware execution time
+        // Closed-loop step: samples the commanded configuration, threads the
+        // planner's finished flag through, and checks path/goal tolerances.
+        let (command, status) =
+            brain.execute_cartesian_step(0.1, &feedback, PATH_TOLERANCE, GOAL_TOLERANCE);
+
+        if let Some(config) = command {
+            println!("Executing step: {:?}", config);
+            let joints: Vec<JointState> = config
+                .iter()
+                .map(|&angle| JointState { angle, ..Default::default() })
+                .collect();
+            if let Err(e) = comms.publish_joint_command(&joints).await {
+                eprintln!("Failed to publish command: {}", e);
+            }
+            // Simulate hardware execution time.
             sleep(Duration::from_millis(100)).await;
-        } else {
-            println!("Motion complete. Waiting for new commands...");
-            sleep(Duration::from_secs(1)).await;
+        }
+
+        match status {
+            ExecutionStatus::InProgress => {}
+            ExecutionStatus::Succeeded => {
+                println!("Motion complete: goal reached within tolerance.");
+                break;
+            }
+            ExecutionStatus::Aborted { joint, error } => {
+                eprintln!("Aborting: joint {} tracking error {:.3}", joint, error);
+                break;
+            }
         }
     }
+
+    Ok(())
 }
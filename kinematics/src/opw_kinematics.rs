@@ -28,6 +28,37 @@ impl OpwKinematicsSolver {
         solver.forward(joints)
     }
 
+    /// Selects the verified IK branch whose joint configuration is closest to
+    /// `current`, so the robot takes the smallest possible move to reach `pose`.
+    ///
+    /// Each branch returned by [`inverse_kinematics`](Self::inverse_kinematics)
+    /// is first filtered through [`verify_solution`](Self::verify_solution),
+    /// then scored by the summed squared shortest angular distance to `current`.
+    /// Per joint the signed difference is wrapped into `[-π, π]` via
+    /// `atan2(sin(d), cos(d))` so wrap-around flips do not look like large
+    /// motions. Returns `None` when no branch verifies.
+    pub fn best_solution(&self, pose: &Isometry3<f64>, current: &[f64; 6]) -> Option<[f64; 6]> {
+        let mut best: Option<([f64; 6], f64)> = None;
+        for solution in self.inverse_kinematics(pose) {
+            if !self.verify_solution(pose, &solution) {
+                continue;
+            }
+            let cost: f64 = solution
+                .iter()
+                .zip(current.iter())
+                .map(|(s, c)| {
+                    let d = s - c;
+                    let wrapped = d.sin().atan2(d.cos());
+                    wrapped * wrapped
+                })
+                .sum();
+            if best.as_ref().is_none_or(|(_, best_cost)| cost < *best_cost) {
+                best = Some((solution, cost));
+            }
+        }
+        best.map(|(solution, _)| solution)
+    }
+
     /// Verifies that the calculated joint angles result in the target pose
     /// using the transformation product T_{base}^{tip} = \prod_{i=1}^{6} A_{i}(\theta_i).
     /// This is effectively what forward_kinematics computes.
@@ -75,4 +106,31 @@ mod tests {
         }
 
     }
+
+    #[test]
+    fn test_best_solution_picks_nearest() {
+        let solver = OpwKinematicsSolver::new(
+            0.550, // c1
+            0.550, // c2
+            0.600, // c3
+            0.110, // c4
+            0.150, // a1
+            0.000, // a2
+            0.000  // b
+        );
+
+        let joints = [0.1, 0.2, 0.3, 0.4, 0.5, 0.6];
+        let target_pose = solver.forward_kinematics(&joints);
+
+        // Seeding with the generating configuration should recover it closely.
+        let best = solver
+            .best_solution(&target_pose, &joints)
+            .expect("expected a verified IK solution");
+
+        for (chosen, expected) in best.iter().zip(joints.iter()) {
+            let d = chosen - expected;
+            let wrapped = d.sin().atan2(d.cos());
+            assert!(wrapped.abs() < 1e-4, "joint off by {}", wrapped);
+        }
+    }
 }